@@ -0,0 +1,858 @@
+// File: src/lib.rs
+// Description: Library core for compact-ts: encodes/decodes the
+// YY-DOY-BASEMIN compact timestamp scheme and provides the flexible
+// timestamp parser used by the `generate` and `expand` CLI commands.
+
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use regex::Regex;
+
+// SECTION 1: ERROR TYPE
+// =====================
+
+/// Errors produced while encoding, decoding, or parsing compact timestamps.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompactError {
+    /// A requested numerical base was not an integer in `2..=36`.
+    InvalidBase(String),
+    /// No compact timestamp matched the expected pattern in the input string.
+    NoTimestampFound(String),
+    /// A BASEMIN segment contained a digit invalid for its base.
+    InvalidDigits { value: String, base: u8 },
+    /// A decoded BASEMIN value was `>= 1440` (minutes in a day).
+    InvalidMinutes { value: String, minutes: u32 },
+    /// A decoded day-of-year was out of range for its year (e.g. 366 in a non-leap year).
+    InvalidDayOfYear(u32),
+    /// None of the known timestamp formats matched the input string.
+    UnparsableTimestamp(String),
+    /// A naive local time fell in a DST fold and `AmbiguityPolicy::Error` was in effect.
+    AmbiguousLocalTime(String),
+    /// A naive local time fell in a DST gap and `AmbiguityPolicy::Error` was in effect.
+    NonexistentLocalTime(String),
+    /// A DST gap could not be resolved to a nearby valid instant.
+    UnresolvedNonexistentLocalTime(String),
+    /// The requested output format string was invalid for this crate's precision or malformed.
+    InvalidOutputFormat(String),
+    /// `--ambiguity` was given a value other than `error`, `earliest`, or `latest`.
+    InvalidAmbiguityPolicy(String),
+    /// A `--locale` tag did not match a known chrono locale.
+    UnknownLocale(String),
+    /// A `--locale` was given but the crate was not built with the `unstable-locales` feature.
+    LocaleUnsupported(String),
+}
+
+impl std::fmt::Display for CompactError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompactError::InvalidBase(value) => {
+                write!(f, "Invalid base '{}': must be an integer between 2 and 36.", value)
+            }
+            CompactError::NoTimestampFound(input) => {
+                write!(f, "No compact timestamp found in \"{}\".", input)
+            }
+            CompactError::InvalidDigits { value, base } => {
+                write!(f, "Invalid digit found in '{}' for base {}.", value, base)
+            }
+            CompactError::InvalidMinutes { value, minutes } => write!(
+                f,
+                "Invalid minutes value '{}' ({} decimal), must be less than 1440.",
+                value, minutes
+            ),
+            CompactError::InvalidDayOfYear(doy) => write!(f, "Invalid day of year: {}.", doy),
+            CompactError::UnparsableTimestamp(input) => {
+                write!(f, "Could not parse '{}' as a valid timestamp.", input)
+            }
+            CompactError::AmbiguousLocalTime(ndt) => write!(
+                f,
+                "Ambiguous local time '{}' (falls in a DST fold); use --ambiguity to pick one.",
+                ndt
+            ),
+            CompactError::NonexistentLocalTime(ndt) => write!(
+                f,
+                "Nonexistent local time '{}' (falls in a DST gap); use --ambiguity to snap to a valid instant.",
+                ndt
+            ),
+            CompactError::UnresolvedNonexistentLocalTime(ndt) => {
+                write!(f, "Could not resolve nonexistent local time near '{}'.", ndt)
+            }
+            CompactError::InvalidOutputFormat(message) => write!(f, "{}", message),
+            CompactError::InvalidAmbiguityPolicy(value) => write!(
+                f,
+                "Unknown ambiguity policy '{}': expected 'error', 'earliest', or 'latest'.",
+                value
+            ),
+            CompactError::UnknownLocale(tag) => write!(f, "Unknown locale tag '{}'.", tag),
+            CompactError::LocaleUnsupported(tag) => write!(
+                f,
+                "Locale '{}' requires building compact-ts with the `unstable-locales` feature.",
+                tag
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+// SECTION 2: BASE
+// ===============
+
+/// A numerical base (radix) for encoding the minutes-since-midnight
+/// component of a compact timestamp. Any radix in `2..=36` is supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Base(u8);
+
+impl Base {
+    /// Base-12 (Duodecimal): 0-9, A-B. Optimal for 3 chars (max value 9BB).
+    pub const B12: Base = Base(12);
+    /// Base-36: 0-9, A-Z. Inefficient for 3 chars (max value 13Z).
+    pub const B36: Base = Base(36);
+
+    /// Builds a `Base` from a radix, rejecting anything outside `2..=36`.
+    pub fn new(radix: u8) -> Result<Self, CompactError> {
+        if (2..=36).contains(&radix) {
+            Ok(Base(radix))
+        } else {
+            Err(CompactError::InvalidBase(radix.to_string()))
+        }
+    }
+
+    /// The underlying radix.
+    pub fn radix(self) -> u8 {
+        self.0
+    }
+}
+
+impl std::str::FromStr for Base {
+    type Err = CompactError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let radix: u8 = s.parse().map_err(|_| CompactError::InvalidBase(s.to_string()))?;
+        Base::new(radix)
+    }
+}
+
+// SECTION 3: AMBIGUITY POLICY
+// ============================
+
+/// Defines how to resolve a `chrono::LocalResult` that is not a single,
+/// unambiguous instant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmbiguityPolicy {
+    /// Fail with an error (the default; preserves prior behavior).
+    Error,
+    /// On a DST fold, take the earlier instant; on a gap, the nearest valid
+    /// instant before it.
+    Earliest,
+    /// On a DST fold, take the later instant; on a gap, the nearest valid
+    /// instant after it.
+    Latest,
+}
+
+impl std::str::FromStr for AmbiguityPolicy {
+    type Err = CompactError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(AmbiguityPolicy::Error),
+            "earliest" => Ok(AmbiguityPolicy::Earliest),
+            "latest" => Ok(AmbiguityPolicy::Latest),
+            _ => Err(CompactError::InvalidAmbiguityPolicy(s.to_string())),
+        }
+    }
+}
+
+/// Resolves a `chrono::LocalResult` produced by interpreting `ndt` in `tz`,
+/// applying `policy` when the local time is ambiguous (a DST fold, where two
+/// instants share the same wall clock reading) or nonexistent (a DST gap).
+///
+/// On a gap, `Earliest`/`Latest` walk outward minute by minute to the nearest
+/// valid instant before/after `ndt`, since no instant actually has that wall
+/// clock reading.
+pub fn resolve_ambiguity<Tz: TimeZone>(
+    tz: &Tz,
+    ndt: NaiveDateTime,
+    policy: AmbiguityPolicy,
+) -> Result<DateTime<Tz>, CompactError> {
+    match tz.from_local_datetime(&ndt) {
+        chrono::LocalResult::Single(dt) => Ok(dt),
+        chrono::LocalResult::Ambiguous(earliest, latest) => match policy {
+            AmbiguityPolicy::Error => Err(CompactError::AmbiguousLocalTime(ndt.to_string())),
+            AmbiguityPolicy::Earliest => Ok(earliest),
+            AmbiguityPolicy::Latest => Ok(latest),
+        },
+        chrono::LocalResult::None => match policy {
+            AmbiguityPolicy::Error => Err(CompactError::NonexistentLocalTime(ndt.to_string())),
+            AmbiguityPolicy::Earliest => nearest_valid_instant(tz, ndt, -1),
+            AmbiguityPolicy::Latest => nearest_valid_instant(tz, ndt, 1),
+        },
+    }
+}
+
+/// Walks outward from `start` in `step_minutes` increments until `tz` maps
+/// the candidate wall clock time to a single valid instant.
+pub fn nearest_valid_instant<Tz: TimeZone>(
+    tz: &Tz,
+    start: NaiveDateTime,
+    step_minutes: i64,
+) -> Result<DateTime<Tz>, CompactError> {
+    let mut candidate = start;
+    for _ in 0..180 {
+        candidate += chrono::Duration::minutes(step_minutes);
+        if let chrono::LocalResult::Single(dt) = tz.from_local_datetime(&candidate) {
+            return Ok(dt);
+        }
+    }
+    Err(CompactError::UnresolvedNonexistentLocalTime(start.to_string()))
+}
+
+// SECTION 4: ENCODE / DECODE
+// ===========================
+
+/// How many BASEMIN digits are needed to represent every minute of a day
+/// (`0..1440`) in the given radix.
+fn digits_for_minutes(base: u8) -> usize {
+    let base = base as u32;
+    let mut n = 1439u32;
+    let mut width = 1;
+    while n >= base {
+        n /= base;
+        width += 1;
+    }
+    width
+}
+
+/// Builds the regex character class matching a single BASEMIN digit in the
+/// given radix, e.g. `[0-9]` for base 10 or `[0-9A-Ba-b]` for base 12.
+fn digit_class_pattern(base: u8) -> String {
+    let base = base as usize;
+    let mut class = format!("0-{}", base.min(10) - 1);
+    if base > 10 {
+        let letter_count = (base - 10) as u8;
+        let last_upper = (b'A' + letter_count - 1) as char;
+        let last_lower = (b'a' + letter_count - 1) as char;
+        class.push_str(&format!("A-{}a-{}", last_upper, last_lower));
+    }
+    format!("[{}]", class)
+}
+
+/// Converts a non-negative integer to a string in the specified base.
+fn to_base_n(mut n: u32, base: u32) -> String {
+    if n == 0 {
+        return "0".to_string();
+    }
+
+    const CHARSET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut result = String::new();
+
+    while n > 0 {
+        result.push(CHARSET[(n % base) as usize] as char);
+        n /= base;
+    }
+
+    result.chars().rev().collect()
+}
+
+/// Converts a string in a given base to a non-negative integer.
+fn from_base_n(s: &str, base: u32) -> Result<u32, CompactError> {
+    u32::from_str_radix(s, base).map_err(|_| CompactError::InvalidDigits {
+        value: s.to_string(),
+        base: base as u8,
+    })
+}
+
+/// Encodes `dt` as a compact `YY-DOY-BASEMIN` timestamp using `base` for the
+/// minutes-since-midnight component.
+pub fn encode(dt: DateTime<impl TimeZone>, base: Base) -> String {
+    let yy = dt.year().rem_euclid(100);
+    let doy = dt.ordinal();
+    let minutes_since_midnight = dt.time().num_seconds_from_midnight() / 60;
+
+    let base_min_str = to_base_n(minutes_since_midnight, base.radix() as u32);
+    let width = digits_for_minutes(base.radix());
+
+    format!("{:02}-{:03}-{:0>width$}", yy, doy, base_min_str, width = width)
+}
+
+/// Finds and decodes a compact `YY-DOY-BASEMIN` timestamp embedded anywhere
+/// in `s`, assuming `base` for the minutes-since-midnight component.
+///
+/// Returns the naive (zone-less) decoded time; the caller is responsible for
+/// interpreting it in whatever zone the value was minted in.
+pub fn decode(s: &str, base: Base) -> Result<NaiveDateTime, CompactError> {
+    let width = digits_for_minutes(base.radix());
+    let digit_class = digit_class_pattern(base.radix());
+    let pattern = format!(r"(\d{{2}})-(\d{{3}})-({}{{{}}})", digit_class, width);
+    let re = Regex::new(&pattern).expect("generated BASEMIN pattern is always a valid regex");
+
+    let caps = re
+        .captures(s)
+        .ok_or_else(|| CompactError::NoTimestampFound(s.to_string()))?;
+
+    let yy_str = &caps[1];
+    let doy_str = &caps[2];
+    let basemin_str = &caps[3];
+
+    let year = 2000 + yy_str.parse::<i32>().expect("regex ensures this is \\d{2}");
+    let doy = doy_str.parse::<u32>().expect("regex ensures this is \\d{3}");
+    let minutes_since_midnight = from_base_n(basemin_str, base.radix() as u32)?;
+
+    if minutes_since_midnight >= 1440 {
+        return Err(CompactError::InvalidMinutes {
+            value: basemin_str.to_string(),
+            minutes: minutes_since_midnight,
+        });
+    }
+
+    let date =
+        NaiveDate::from_yo_opt(year, doy).ok_or(CompactError::InvalidDayOfYear(doy))?;
+    Ok(date
+        .and_hms_opt(minutes_since_midnight / 60, minutes_since_midnight % 60, 0)
+        .expect("minutes_since_midnight < 1440 guarantees a valid time of day"))
+}
+
+// SECTION 5: FLEXIBLE TIMESTAMP PARSING
+// =======================================
+
+/// Smartly parses a string into a local DateTime object.
+///
+/// It tries a series of common timestamp formats in order of specificity.
+/// If a date-only format is matched, the time is assumed to be midnight.
+/// All parsed times are converted to the system's local timezone, resolving
+/// any ambiguous or nonexistent local time per `ambiguity`.
+pub fn parse_flexible_timestamp(
+    s: &str,
+    ambiguity: AmbiguityPolicy,
+) -> Result<DateTime<Local>, CompactError> {
+    // Helper to convert a NaiveDateTime to a local DateTime, handling ambiguity.
+    let to_local = |ndt: NaiveDateTime| resolve_ambiguity(&Local, ndt, ambiguity);
+
+    // Attempt 1: Full ISO 8601 / RFC 3339 (e.g., 2025-06-30T22:42:05Z)
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    // Attempt 2: RFC 2822 (e.g., Mon, 30 Jun 2025 22:42:05 +0200), as seen in
+    // email headers, HTTP, and `git log` / `date -R` output.
+    if let Ok(dt) = DateTime::parse_from_rfc2822(s) {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    // Attempt 3: ISO 8601 with offset, no seconds (e.g., 2025-06-28T20:28+02:00)
+    if let Ok(dt) = DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M%z") {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    // Attempt 4: Compact date, time with colon, offset, no seconds (e.g., 20250628T20:28+02:00)
+    if let Ok(dt) = DateTime::parse_from_str(s, "%Y%m%dT%H:%M%z") {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    // Attempt 5: Space-separated date and time, offset, no seconds (e.g.,
+    // 2025-06-28 20:28+02:00), mirroring attempt 3 with a space in place of
+    // the 'T' separator.
+    if let Ok(dt) = DateTime::parse_from_str(s, "%Y-%m-%d %H:%M%z") {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    // Attempt 6: ISO 8601 compact with Z (e.g., 20250630T224205Z)
+    // Note: The 'Z' is a literal, not a timezone name for `%Z`.
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ") {
+        return Ok(Utc.from_utc_datetime(&dt).with_timezone(&Local));
+    }
+
+    // Attempt 7: Naive date and time with seconds (e.g., 2025-06-30T22:42:05)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") {
+        return to_local(ndt);
+    }
+
+    // Attempt 8: Naive date and time, no seconds, with colon (e.g., 2025-06-30T22:42)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M") {
+        return to_local(ndt);
+    }
+
+    // Attempt 9: Naive date and time with seconds, space-separated (e.g.,
+    // 2025-06-30 22:42:05), as produced by `date` and common log/SQL
+    // timestamps. Kept losslessly round-trippable with the seconds intact,
+    // even though the compact encoding itself only retains minute precision.
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+        return to_local(ndt);
+    }
+
+    // Attempt 10: Naive date and time, no seconds, space-separated (e.g., 2025-06-30 22:42)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        return to_local(ndt);
+    }
+
+    // Attempt 11: Naive date and time, no seconds, compact time (e.g., 2025-06-30T2242)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H%M") {
+        return to_local(ndt);
+    }
+
+    // Attempt 12: Compact date, naive time, no seconds, with colon (e.g., 20250630T22:42)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H:%M") {
+        return to_local(ndt);
+    }
+
+    // Attempt 13: Fully compact date and time, no seconds (e.g., 20250630T2242)
+    if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M") {
+        return to_local(ndt);
+    }
+
+    // Attempt 14: Date-only with hyphens (e.g., 2025-06-30)
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let dt = date.and_hms_opt(0, 0, 0).unwrap();
+        return to_local(dt);
+    }
+
+    // Attempt 15: Date-only compact (e.g., 20250630)
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y%m%d") {
+        let dt = date.and_hms_opt(0, 0, 0).unwrap();
+        return to_local(dt);
+    }
+
+    Err(CompactError::UnparsableTimestamp(s.to_string()))
+}
+
+// SECTION 6: OUTPUT FORMATTING
+// ==============================
+
+/// Composite specifiers that chrono expands to a sequence including seconds
+/// (e.g. `%T` = `%H:%M:%S`). Checked explicitly in addition to walking the
+/// parsed items, since the crate has no sub-minute precision to render.
+const SECOND_BEARING_COMPOSITES: &[&str] = &["%T", "%X", "%r", "%+"];
+
+/// Validates that the format string does not request unsupported precision.
+///
+/// Parses `format` with `chrono::format::StrftimeItems` and rejects it if any
+/// item is a seconds/nanoseconds specifier, directly (`%S`, `%s`, `%f`) or via
+/// a composite specifier that expands to one (`%T`, `%X`, `%r`, `%+`).
+/// Malformed format strings are surfaced as an `Err` rather than left for
+/// chrono to render as literal garbage at print time.
+pub fn validate_format_string(format: &str) -> Result<(), CompactError> {
+    for composite in SECOND_BEARING_COMPOSITES {
+        if format.contains(composite) {
+            return Err(CompactError::InvalidOutputFormat(format!(
+                "Output format string cannot contain '{}', which expands to include seconds.",
+                composite
+            )));
+        }
+    }
+
+    for item in StrftimeItems::new(format) {
+        match item {
+            Item::Numeric(Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp, _)
+            | Item::Fixed(
+                Fixed::Nanosecond | Fixed::Nanosecond3 | Fixed::Nanosecond6 | Fixed::Nanosecond9,
+            ) => {
+                return Err(CompactError::InvalidOutputFormat(
+                    "Output format string cannot contain second or sub-second specifiers (%S, %s, %f)."
+                        .to_string(),
+                ));
+            }
+            Item::Error => {
+                return Err(CompactError::InvalidOutputFormat(format!(
+                    "Invalid output format string: '{}'.",
+                    format
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats `dt` with `format`, optionally localizing weekday/month names via
+/// `locale` (a BCP-47-ish tag such as `fr_FR`, requiring the
+/// `unstable-locales` feature).
+pub fn format_expanded<Tz: TimeZone>(
+    dt: &DateTime<Tz>,
+    format: &str,
+    locale: Option<&str>,
+) -> Result<String, CompactError>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    match locale {
+        Some(tag) => format_localized(dt, format, tag),
+        None => Ok(dt.format(format).to_string()),
+    }
+}
+
+#[cfg(feature = "unstable-locales")]
+fn format_localized<Tz: TimeZone>(
+    dt: &DateTime<Tz>,
+    format: &str,
+    tag: &str,
+) -> Result<String, CompactError>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let locale: chrono::Locale = tag
+        .parse()
+        .map_err(|_| CompactError::UnknownLocale(tag.to_string()))?;
+    Ok(dt
+        .format_localized_with_items(StrftimeItems::new_with_locale(format, locale), locale)
+        .to_string())
+}
+
+#[cfg(not(feature = "unstable-locales"))]
+fn format_localized<Tz: TimeZone>(
+    _dt: &DateTime<Tz>,
+    _format: &str,
+    tag: &str,
+) -> Result<String, CompactError>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    Err(CompactError::LocaleUnsupported(tag.to_string()))
+}
+
+// SECTION 7: UNIT TESTS
+// =====================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to create a UTC DateTime for consistent test assertions.
+    fn make_utc_dt(year: i32, month: u32, day: u32, h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_full_iso_with_hyphens() {
+        let input = "2025-06-30T22:42:05Z";
+        let expected = make_utc_dt(2025, 6, 30, 22, 42, 5);
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.with_timezone(&Utc), expected);
+    }
+
+    #[test]
+    fn test_parse_full_iso_with_offset() {
+        let input = "2025-07-01T04:12:05+05:30"; // India Standard Time
+        let expected = make_utc_dt(2025, 6, 30, 22, 42, 5);
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.with_timezone(&Utc), expected);
+    }
+
+    #[test]
+    fn test_parse_rfc2822() {
+        let input = "Mon, 30 Jun 2025 22:42:05 +0200";
+        let expected = make_utc_dt(2025, 6, 30, 20, 42, 5);
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.with_timezone(&Utc), expected);
+    }
+
+    #[test]
+    fn test_parse_compact_iso_with_z() {
+        // Note: RFC3339 parser does NOT handle this compact form.
+        let input = "20250630T224205Z";
+        let expected = make_utc_dt(2025, 6, 30, 22, 42, 5);
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.with_timezone(&Utc), expected);
+    }
+
+    #[test]
+    fn test_parse_date_only_with_hyphens() {
+        let input = "2025-06-30";
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.year(), 2025);
+        assert_eq!(parsed.month(), 6);
+        assert_eq!(parsed.day(), 30);
+        assert_eq!(parsed.hour(), 0);
+        assert_eq!(parsed.minute(), 0);
+    }
+
+    #[test]
+    fn test_parse_date_only_compact() {
+        let input = "20250630";
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.year(), 2025);
+        assert_eq!(parsed.month(), 6);
+        assert_eq!(parsed.day(), 30);
+        assert_eq!(parsed.hour(), 0);
+        assert_eq!(parsed.minute(), 0);
+    }
+
+    #[test]
+    fn test_parse_naive_datetime() {
+        let input = "2025-06-30T22:42:05";
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.year(), 2025);
+        assert_eq!(parsed.month(), 6);
+        assert_eq!(parsed.day(), 30);
+        assert_eq!(parsed.hour(), 22);
+        assert_eq!(parsed.minute(), 42);
+    }
+
+    #[test]
+    fn test_parse_naive_datetime_space_separated() {
+        let input = "2025-06-30 22:42:05";
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.year(), 2025);
+        assert_eq!(parsed.month(), 6);
+        assert_eq!(parsed.day(), 30);
+        assert_eq!(parsed.hour(), 22);
+        assert_eq!(parsed.minute(), 42);
+    }
+
+    #[test]
+    fn test_parse_naive_datetime_space_separated_no_seconds() {
+        let input = "2025-06-30 22:42";
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.year(), 2025);
+        assert_eq!(parsed.month(), 6);
+        assert_eq!(parsed.day(), 30);
+        assert_eq!(parsed.hour(), 22);
+        assert_eq!(parsed.minute(), 42);
+    }
+
+    #[test]
+    fn test_parse_space_separated_with_offset() {
+        let input = "2025-06-28 20:28+02:00";
+        let expected = make_utc_dt(2025, 6, 28, 18, 28, 0);
+        let parsed = parse_flexible_timestamp(input, AmbiguityPolicy::Error).unwrap();
+        assert_eq!(parsed.with_timezone(&Utc), expected);
+    }
+
+    #[test]
+    fn test_parse_invalid_string() {
+        let input = "not-a-real-date";
+        assert!(parse_flexible_timestamp(input, AmbiguityPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_resolve_ambiguity_single_is_policy_independent() {
+        // Utc never produces Ambiguous/None, so every policy must agree.
+        let ndt = make_utc_dt(2025, 6, 30, 22, 42, 5).naive_utc();
+        for policy in [
+            AmbiguityPolicy::Error,
+            AmbiguityPolicy::Earliest,
+            AmbiguityPolicy::Latest,
+        ] {
+            let resolved = resolve_ambiguity(&Utc, ndt, policy).unwrap();
+            assert_eq!(resolved, make_utc_dt(2025, 6, 30, 22, 42, 5));
+        }
+    }
+
+    #[test]
+    fn test_resolve_ambiguity_fold() {
+        // 2025-11-02 01:30 America/New_York is covered twice: once before the
+        // fall-back (EDT, UTC-4) and once after (EST, UTC-5).
+        let tz = chrono_tz::America::New_York;
+        let ndt = NaiveDate::from_ymd_opt(2025, 11, 2)
+            .unwrap()
+            .and_hms_opt(1, 30, 0)
+            .unwrap();
+
+        let earliest = resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Earliest).unwrap();
+        assert_eq!(earliest.with_timezone(&Utc), make_utc_dt(2025, 11, 2, 5, 30, 0));
+
+        let latest = resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Latest).unwrap();
+        assert_eq!(latest.with_timezone(&Utc), make_utc_dt(2025, 11, 2, 6, 30, 0));
+
+        assert!(matches!(
+            resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Error),
+            Err(CompactError::AmbiguousLocalTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_ambiguity_gap() {
+        // 2025-03-09 02:30 America/New_York never occurs: the spring-forward
+        // jumps the clock from 02:00 straight to 03:00.
+        let tz = chrono_tz::America::New_York;
+        let ndt = NaiveDate::from_ymd_opt(2025, 3, 9)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+
+        let earliest = resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Earliest).unwrap();
+        assert_eq!(earliest.with_timezone(&Utc), make_utc_dt(2025, 3, 9, 6, 59, 0));
+
+        let latest = resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Latest).unwrap();
+        assert_eq!(latest.with_timezone(&Utc), make_utc_dt(2025, 3, 9, 7, 0, 0));
+
+        assert!(matches!(
+            resolve_ambiguity(&tz, ndt, AmbiguityPolicy::Error),
+            Err(CompactError::NonexistentLocalTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_expand_with_explicit_timezone() {
+        // Exercises the `decode` -> `resolve_ambiguity(&tz, ...)` ->
+        // `format_expanded` pipeline that `--timezone` drives, end to end.
+        // Compared against `Utc` rather than `Local`, since `Local` depends
+        // on the machine running the test and would make this flaky.
+        let encoded = encode(make_utc_dt(2025, 6, 30, 22, 42, 0), Base::B12);
+        let naive = decode(&encoded, Base::B12).unwrap();
+        let format = "%Y-%m-%dT%H:%M%z";
+
+        let paris = chrono_tz::Europe::Paris;
+        let paris_dt = resolve_ambiguity(&paris, naive, AmbiguityPolicy::Error).unwrap();
+        let paris_rendered = format_expanded(&paris_dt, format, None).unwrap();
+        assert_eq!(paris_rendered, "2025-06-30T22:42+0200"); // CEST in June
+
+        let utc_dt = resolve_ambiguity(&Utc, naive, AmbiguityPolicy::Error).unwrap();
+        let utc_rendered = format_expanded(&utc_dt, format, None).unwrap();
+        assert_eq!(utc_rendered, "2025-06-30T22:42+0000");
+
+        assert_ne!(paris_rendered, utc_rendered);
+    }
+
+    #[test]
+    fn test_ambiguity_policy_from_str() {
+        assert_eq!("error".parse::<AmbiguityPolicy>().unwrap(), AmbiguityPolicy::Error);
+        assert_eq!("Earliest".parse::<AmbiguityPolicy>().unwrap(), AmbiguityPolicy::Earliest);
+        assert_eq!("LATEST".parse::<AmbiguityPolicy>().unwrap(), AmbiguityPolicy::Latest);
+        assert!("sometimes".parse::<AmbiguityPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_base_new_rejects_out_of_range() {
+        assert!(Base::new(1).is_err());
+        assert!(Base::new(37).is_err());
+        assert!(Base::new(12).is_ok());
+    }
+
+    #[test]
+    fn test_base_from_str() {
+        assert_eq!("12".parse::<Base>().unwrap(), Base::B12);
+        assert!("0".parse::<Base>().is_err());
+        assert!("abc".parse::<Base>().is_err());
+    }
+
+    #[test]
+    fn test_base_conversion_to_base() {
+        // 22*60 + 42 = 1362 minutes
+        assert_eq!(to_base_n(1362, 12), "956");
+        assert_eq!(to_base_n(1362, 36), "11U");
+        // 20*60 + 48 = 1248 minutes
+        assert_eq!(to_base_n(1248, 12), "880");
+        assert_eq!(to_base_n(1248, 36), "YO");
+    }
+
+    // --- Tests for `decode` logic ---
+
+    #[test]
+    fn test_base_conversion_from_base() {
+        assert_eq!(from_base_n("956", 12).unwrap(), 1362);
+        assert_eq!(from_base_n("11U", 36).unwrap(), 1362);
+        assert_eq!(from_base_n("880", 12).unwrap(), 1248);
+        assert_eq!(from_base_n("YO", 36).unwrap(), 1248);
+        assert_eq!(from_base_n("yo", 36).unwrap(), 1248); // case-insensitive
+    }
+
+    #[test]
+    fn test_base_conversion_from_base_invalid() {
+        assert!(from_base_n("95C", 12).is_err()); // C is not in base 12
+        assert!(from_base_n("11$", 36).is_err()); // $ is not in base 36
+    }
+
+    #[test]
+    fn test_validate_format_string() {
+        assert!(validate_format_string("%Y-%m-%d %H:%M").is_ok());
+        assert!(validate_format_string("%A, %B %d").is_ok());
+        assert!(validate_format_string("hello world").is_ok()); // no specifiers is ok
+    }
+
+    #[test]
+    fn test_validate_format_string_invalid() {
+        assert!(validate_format_string("%Y-%m-%d %H:%M:%S").is_err()); // has %S
+        assert!(validate_format_string("%Y-%m-%d %H:%M:%S.%f").is_err()); // has %f
+        assert!(validate_format_string("%s").is_err()); // has %s
+    }
+
+    #[test]
+    fn test_validate_format_string_rejects_composite_specifiers() {
+        assert!(validate_format_string("%Y-%m-%dT%T").is_err()); // %T = %H:%M:%S
+        assert!(validate_format_string("%X").is_err()); // locale time, usually with seconds
+        assert!(validate_format_string("%r").is_err()); // 12-hour clock with seconds
+        assert!(validate_format_string("%+").is_err()); // full RFC 3339-like form
+    }
+
+    #[test]
+    fn test_validate_format_string_invalid_format() {
+        // A trailing '%' is not a valid specifier and has no closing item.
+        assert!(validate_format_string("%Y-%m-%d%").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-locales")]
+    fn test_format_expanded_localized() {
+        let dt = make_utc_dt(2025, 6, 30, 22, 42, 0);
+        let format = "%A %d %B %Y";
+
+        let fr = format_expanded(&dt, format, Some("fr_FR")).unwrap();
+        assert_eq!(fr, "lundi 30 juin 2025");
+
+        let de = format_expanded(&dt, format, Some("de_DE")).unwrap();
+        assert_eq!(de, "Montag 30 Juni 2025");
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-locales")]
+    fn test_format_expanded_unknown_locale() {
+        let dt = make_utc_dt(2025, 6, 30, 22, 42, 0);
+        let result = format_expanded(&dt, "%A %d %B %Y", Some("xx_XX"));
+        assert!(matches!(result, Err(CompactError::UnknownLocale(tag)) if tag == "xx_XX"));
+    }
+
+    #[test]
+    fn test_decode_b12() {
+        // 2025-06-30 is day 181. 22:42 is 1362 minutes, which is 956 in base 12.
+        let result = decode("25-181-956", Base::B12).unwrap();
+        assert_eq!(result.year(), 2025);
+        assert_eq!(result.month(), 6);
+        assert_eq!(result.day(), 30);
+        assert_eq!(result.hour(), 22);
+        assert_eq!(result.minute(), 42);
+    }
+
+    #[test]
+    fn test_decode_b36() {
+        // 2025-06-30 is day 181. 22:42 is 1362 minutes, which is 11U in base 36.
+        let result = decode("prefix-25-181-11U-suffix", Base::B36).unwrap();
+        assert_eq!(result.year(), 2025);
+        assert_eq!(result.month(), 6);
+        assert_eq!(result.day(), 30);
+        assert_eq!(result.hour(), 22);
+        assert_eq!(result.minute(), 42);
+    }
+
+    #[test]
+    fn test_decode_invalid_doy() {
+        // 2025 is not a leap year, so 366 is invalid.
+        assert!(decode("25-366-000", Base::B12).is_err());
+        // 2024 is a leap year.
+        assert!(decode("24-366-000", Base::B12).is_ok());
+    }
+
+    #[test]
+    fn test_decode_invalid_minutes() {
+        // AAA in base 12 is 10*144 + 10*12 + 10 = 1570, which is > 1439.
+        let result = decode("25-181-AAA", Base::B12);
+        assert!(matches!(result, Err(CompactError::InvalidMinutes { .. })));
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip_b12() {
+        let dt = make_utc_dt(2025, 6, 30, 22, 42, 0);
+        let encoded = encode(dt, Base::B12);
+        assert_eq!(encoded, "25-181-956");
+        let decoded = decode(&encoded, Base::B12).unwrap();
+        assert_eq!(decoded, dt.naive_utc());
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip_arbitrary_base() {
+        // Base 16 is neither of the two choices the old CLI ValueEnum offered.
+        let base = Base::new(16).unwrap();
+        let dt = make_utc_dt(2025, 6, 30, 22, 42, 0);
+        let encoded = encode(dt, base);
+        let decoded = decode(&encoded, base).unwrap();
+        assert_eq!(decoded, dt.naive_utc());
+    }
+}